@@ -1,15 +1,134 @@
-use clap::Parser;
+use async_trait::async_trait;
+use clap::{Parser, ValueEnum};
 use futures::future::select_all;
-use futures::FutureExt;
-use std::collections::VecDeque;
+use futures::{ready, FutureExt};
+use socket2::{Domain, Protocol as SockProtocol, Socket, Type};
+use std::collections::{HashSet, VecDeque};
 use std::fs::File;
-use std::io::BufRead;
-use std::time::{Duration, Instant};
+use std::io::{self, BufRead};
+use std::iter::from_fn;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket as TokioUdpSocket;
 use tokio::time::sleep;
 use trust_dns_resolver::error::ResolveErrorKind;
-use trust_dns_resolver::name_server::{GenericConnection, GenericConnectionProvider, TokioRuntime};
+use trust_dns_resolver::proto::op::ResponseCode;
+use trust_dns_resolver::name_server::{
+    GenericConnection, GenericConnectionProvider, RuntimeProvider, TokioHandle, TokioRuntime,
+};
+use trust_dns_resolver::proto::rr::{RData, RecordType};
+use trust_dns_resolver::proto::udp::UdpSocket;
+use trust_dns_resolver::proto::TokioTime;
 use trust_dns_resolver::{config::*, AsyncResolver};
 
+/// Resolver handle backed by the custom [`MarkRuntime`] socket provider.
+type Resolver = AsyncResolver<GenericConnection, GenericConnectionProvider<MarkRuntime>>;
+
+/// Socket-creation knobs for the resolver's UDP sockets.
+///
+/// trust-dns's [`UdpSocket`] trait only hands us a `SocketAddr`, so the
+/// operator-supplied `--bind`/`--so-mark` values are stashed in this process
+/// global and read back when each socket is created.
+#[derive(Default, Clone, Copy)]
+struct SocketOpts {
+    bind: Option<IpAddr>,
+    so_mark: Option<u32>,
+}
+
+static SOCKET_OPTS: OnceLock<SocketOpts> = OnceLock::new();
+
+fn socket_opts() -> SocketOpts {
+    SOCKET_OPTS.get().copied().unwrap_or_default()
+}
+
+/// A [`RuntimeProvider`] that behaves like [`TokioRuntime`] but hands out
+/// [`MarkUdpSocket`]s whose source address and firewall mark are configurable.
+#[derive(Clone, Copy)]
+struct MarkRuntime;
+
+impl RuntimeProvider for MarkRuntime {
+    type Handle = TokioHandle;
+    type Timer = TokioTime;
+    type Tcp = <TokioRuntime as RuntimeProvider>::Tcp;
+    type Udp = MarkUdpSocket;
+}
+
+/// UDP socket wrapper that applies `SO_MARK` and a custom bind address before
+/// the socket is used, then defers all I/O to [`tokio::net::UdpSocket`].
+struct MarkUdpSocket(TokioUdpSocket);
+
+/// Build a Tokio UDP socket for `addr`'s family, applying the configured
+/// `--bind` source address and `SO_MARK` via socket2 before binding.
+fn build_udp_socket(addr: SocketAddr, bind_addr: SocketAddr) -> io::Result<TokioUdpSocket> {
+    let opts = socket_opts();
+    let domain = match addr {
+        SocketAddr::V4(_) => Domain::IPV4,
+        SocketAddr::V6(_) => Domain::IPV6,
+    };
+
+    let socket = Socket::new(domain, Type::DGRAM, Some(SockProtocol::UDP))?;
+    socket.set_nonblocking(true)?;
+
+    // SO_MARK lets operators tag enumeration traffic for policy routing.
+    #[cfg(target_os = "linux")]
+    if let Some(mark) = opts.so_mark {
+        socket.set_mark(mark)?;
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = opts.so_mark;
+
+    // An explicit --bind address overrides the family-default unspecified bind,
+    // but only for matching-family sockets: binding a v4 source to a v6 upstream
+    // socket (or vice versa) fails and would silently drop those name servers.
+    let local = match opts.bind {
+        Some(ip) if ip.is_ipv4() == addr.is_ipv4() => SocketAddr::new(ip, 0),
+        _ => bind_addr,
+    };
+    socket.bind(&local.into())?;
+
+    TokioUdpSocket::from_std(socket.into())
+}
+
+#[async_trait]
+impl UdpSocket for MarkUdpSocket {
+    async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        let bind_addr = match addr {
+            SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+        };
+        Self::connect_with_bind(addr, bind_addr).await
+    }
+
+    async fn connect_with_bind(addr: SocketAddr, bind_addr: SocketAddr) -> io::Result<Self> {
+        Ok(MarkUdpSocket(build_udp_socket(addr, bind_addr)?))
+    }
+
+    async fn bind(addr: SocketAddr) -> io::Result<Self> {
+        Ok(MarkUdpSocket(build_udp_socket(addr, addr)?))
+    }
+
+    fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        let addr = ready!(self.0.poll_recv_from(cx, &mut buf))?;
+        Poll::Ready(Ok((buf.filled().len(), addr)))
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        self.0.poll_send_to(cx, buf, target)
+    }
+}
+
 /// Asynchronous DNS subdomain enumeration tool
 #[derive(Parser, Debug)]
 #[command(version)]
@@ -22,31 +141,120 @@ struct Args {
     #[arg(short, long)]
     target: String,
 
-    /// Name server to use (example: 1.1.1.1:53)
-    #[arg(short, long)]
-    ns: Option<String>,
+    /// Name server(s) to use, repeatable or comma-separated (example: 1.1.1.1:53)
+    #[arg(short, long, value_delimiter = ',')]
+    ns: Vec<String>,
 
     /// Queries per Second
     #[arg(short, long, default_value_t = 10)]
     qps: u32,
 
+    /// IP version to resolve
+    #[arg(long, value_enum, default_value_t = IpVersion::Both)]
+    ip_version: IpVersion,
+
+    /// Prefer IPv4 addresses first when interleaving (default is IPv6 first)
+    #[arg(long, default_value_t = false)]
+    prefer_ipv4: bool,
+
+    /// Record type to query (default is A/AAAA via address lookup)
+    #[arg(short, long, value_enum)]
+    record_type: Option<RecordTypeArg>,
+
+    /// Omit wildcard hits from stdout instead of printing them
+    #[arg(long, default_value_t = false)]
+    hide_wildcard: bool,
+
+    /// Local address to bind resolver sockets to (steer traffic out a given source)
+    #[arg(long)]
+    bind: Option<IpAddr>,
+
+    /// Apply SO_MARK (firewall/policy-routing mark) to resolver sockets (Linux only)
+    #[arg(long)]
+    so_mark: Option<u32>,
+
     /// Enable debug output
     #[arg(short, long, default_value_t = false)]
     debug: bool,
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+enum IpVersion {
+    V4,
+    V6,
+    Both,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+enum RecordTypeArg {
+    A,
+    Aaaa,
+    Cname,
+    Txt,
+    Mx,
+    Ns,
+}
+
+impl From<RecordTypeArg> for RecordType {
+    fn from(arg: RecordTypeArg) -> RecordType {
+        match arg {
+            RecordTypeArg::A => RecordType::A,
+            RecordTypeArg::Aaaa => RecordType::AAAA,
+            RecordTypeArg::Cname => RecordType::CNAME,
+            RecordTypeArg::Txt => RecordType::TXT,
+            RecordTypeArg::Mx => RecordType::MX,
+            RecordTypeArg::Ns => RecordType::NS,
+        }
+    }
+}
+
+impl IpVersion {
+    fn ip_strategy(self) -> LookupIpStrategy {
+        match self {
+            IpVersion::V4 => LookupIpStrategy::Ipv4Only,
+            IpVersion::V6 => LookupIpStrategy::Ipv6Only,
+            IpVersion::Both => LookupIpStrategy::Ipv4AndIpv6,
+        }
+    }
+}
+
+/// Order addresses per RFC 8305, alternating between IPv6 and IPv4 families.
+///
+/// `pick_v6` seeds which family goes first; each emitted address flips it, and
+/// when one family is exhausted the other drains to completion.
+fn interleave_addresses(addresses: &[IpAddr], mut pick_v6: bool) -> Vec<IpAddr> {
+    let mut v6 = addresses.iter().filter(|a| a.is_ipv6()).copied();
+    let mut v4 = addresses.iter().filter(|a| a.is_ipv4()).copied();
+
+    from_fn(move || {
+        if pick_v6 {
+            pick_v6 = false;
+            v6.next().or_else(|| v4.next())
+        } else {
+            pick_v6 = true;
+            v4.next().or_else(|| v6.next())
+        }
+    })
+    .collect()
+}
+
 #[derive(Debug, Clone)]
 enum ResolveStatus {
     Pending,
     Timeout,
     Resolved,
     CantResolve,
+    DanglingCname,
+    Wildcard,
 }
 
 #[derive(Debug, Clone)]
 struct ResolveTask {
     subdomain: String,
     status: ResolveStatus,
+    addresses: Vec<IpAddr>,
+    records: Vec<String>,
+    cname_target: Option<String>,
 }
 
 impl ResolveTask {
@@ -54,45 +262,153 @@ impl ResolveTask {
         ResolveTask {
             subdomain,
             status: ResolveStatus::Pending,
+            addresses: vec![],
+            records: vec![],
+            cname_target: None,
+        }
+    }
+
+    /// Build a task for `subdomain` with the given status and no resolved data.
+    fn with_status(subdomain: String, status: ResolveStatus) -> ResolveTask {
+        ResolveTask {
+            status,
+            ..ResolveTask::new(subdomain)
         }
     }
 }
 
+/// Map a resolver error to the matching status for a failed lookup.
+fn status_from_error(kind: &ResolveErrorKind) -> ResolveStatus {
+    match kind {
+        ResolveErrorKind::Timeout => ResolveStatus::Timeout,
+        _ => ResolveStatus::CantResolve,
+    }
+}
+
 async fn resolve(
-    resolver: &AsyncResolver<GenericConnection, GenericConnectionProvider<TokioRuntime>>,
+    resolver: &Resolver,
     task: ResolveTask,
     target: &str,
+    record_type: Option<RecordType>,
 ) -> ResolveTask {
     let to_resolve = format!("{}.{}", task.subdomain, target);
-    let response = resolver.lookup_ip(to_resolve).await;
-    match response {
-        Err(e) => match e.kind() {
-            ResolveErrorKind::Timeout => ResolveTask {
-                subdomain: task.subdomain,
-                status: ResolveStatus::Timeout,
-            },
-            ResolveErrorKind::NoRecordsFound {
-                query: _,
-                soa: _,
-                negative_ttl: _,
-                response_code: _,
-                trusted: _,
-            } => ResolveTask {
-                subdomain: task.subdomain,
-                status: ResolveStatus::CantResolve,
-            },
-            _ => ResolveTask {
-                subdomain: task.subdomain,
-                status: ResolveStatus::CantResolve,
-            },
-        },
-        Ok(_) => ResolveTask {
+    match record_type {
+        None => resolve_ip(resolver, task, to_resolve).await,
+        Some(rt) => resolve_record(resolver, task, to_resolve, rt).await,
+    }
+}
+
+/// Default path: resolve A/AAAA addresses via `lookup_ip`.
+async fn resolve_ip(
+    resolver: &Resolver,
+    task: ResolveTask,
+    to_resolve: String,
+) -> ResolveTask {
+    match resolver.lookup_ip(to_resolve).await {
+        Err(e) => ResolveTask::with_status(task.subdomain, status_from_error(e.kind())),
+        Ok(lookup) => ResolveTask {
             subdomain: task.subdomain,
             status: ResolveStatus::Resolved,
+            addresses: lookup.iter().collect(),
+            records: vec![],
+            cname_target: None,
         },
     }
 }
 
+/// Query a specific record type, chasing CNAMEs to flag dangling pointers.
+async fn resolve_record(
+    resolver: &Resolver,
+    task: ResolveTask,
+    to_resolve: String,
+    record_type: RecordType,
+) -> ResolveTask {
+    let lookup = match resolver.lookup(to_resolve, record_type).await {
+        Err(e) => return ResolveTask::with_status(task.subdomain, status_from_error(e.kind())),
+        Ok(lookup) => lookup,
+    };
+
+    let records: Vec<String> = lookup.iter().map(|rdata| rdata.to_string()).collect();
+    let cname_target = lookup.iter().find_map(|rdata| match rdata {
+        RData::CNAME(name) => Some(name.to_string()),
+        _ => None,
+    });
+
+    // When the answer is a CNAME, follow it: an NXDOMAIN on the target while the
+    // CNAME record still exists is the classic subdomain-takeover signal.
+    if let Some(target_name) = &cname_target {
+        // The takeover signal is specifically NXDOMAIN on the target while the
+        // CNAME record still exists; NODATA/NOERROR (name exists, no A/AAAA) and
+        // transient failures like SERVFAIL are not dangling pointers.
+        let status = match resolver.lookup_ip(target_name.clone()).await {
+            Ok(_) => ResolveStatus::Resolved,
+            Err(e) => match e.kind() {
+                ResolveErrorKind::NoRecordsFound { response_code, .. }
+                    if *response_code == ResponseCode::NXDomain =>
+                {
+                    ResolveStatus::DanglingCname
+                }
+                kind => status_from_error(kind),
+            },
+        };
+        return ResolveTask {
+            subdomain: task.subdomain,
+            status,
+            addresses: vec![],
+            records,
+            cname_target,
+        };
+    }
+
+    ResolveTask {
+        subdomain: task.subdomain,
+        status: ResolveStatus::Resolved,
+        addresses: vec![],
+        records,
+        cname_target,
+    }
+}
+
+/// Generate a pseudo-random 16-char DNS label from a seed (no extra deps).
+fn random_label(seed: u64) -> String {
+    const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    let mut state = seed;
+    (0..16)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            CHARS[(state >> 33) as usize % CHARS.len()] as char
+        })
+        .collect()
+}
+
+/// Probe a few random labels to learn a catch-all (wildcard) address set.
+///
+/// Any label that resolves contributes its addresses to the baseline; real hits
+/// whose non-empty address set is a subset of this set are later demoted to
+/// `Wildcard`. Record-type queries carry no addresses and are never demoted.
+async fn detect_wildcard(
+    resolver: &Resolver,
+    target: &str,
+    record_type: Option<RecordType>,
+) -> HashSet<IpAddr> {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+
+    let mut baseline = HashSet::new();
+    for i in 0..3 {
+        let label = random_label(seed.wrapping_add(i));
+        let result = resolve(resolver, ResolveTask::new(label), target, record_type).await;
+        if let ResolveStatus::Resolved = result.status {
+            baseline.extend(result.addresses);
+        }
+    }
+    baseline
+}
+
 /// Read input file and build a list of resolve tasks
 fn read_subdomains(filename: String) -> Result<VecDeque<ResolveTask>, std::io::Error> {
     let file = File::open(filename)?;
@@ -112,17 +428,46 @@ async fn main() {
     // create tasks
     let mut pending_tasks = read_subdomains(args.subdomains).unwrap();
 
-    // init resolver
-    let resolver_config = match args.ns {
-        Some(v) => {
-            let mut ret = ResolverConfig::new();
-            ret.add_name_server(NameServerConfig::new(v.parse().expect("Invalid NS address"), Protocol::Udp));
-            ret
-        },
-        None => ResolverConfig::default(),
+    // publish the socket options before any resolver socket is created
+    SOCKET_OPTS
+        .set(SocketOpts {
+            bind: args.bind,
+            so_mark: args.so_mark,
+        })
+        .ok();
+
+    // init resolvers, one per name server so queries can be rotated between them
+    let mut resolver_opts = ResolverOpts::default();
+    resolver_opts.ip_strategy = args.ip_version.ip_strategy();
+
+    let resolvers: Vec<Resolver> = if args.ns.is_empty() {
+        vec![Resolver::new(
+            ResolverConfig::default(),
+            resolver_opts.clone(),
+            TokioHandle::default(),
+        )
+        .unwrap()]
+    } else {
+        args.ns
+            .iter()
+            .map(|ns| {
+                let mut config = ResolverConfig::new();
+                config.add_name_server(NameServerConfig::new(
+                    ns.parse().expect("Invalid NS address"),
+                    Protocol::Udp,
+                ));
+                Resolver::new(config, resolver_opts.clone(), TokioHandle::default()).unwrap()
+            })
+            .collect()
     };
-    let resolver =
-        AsyncResolver::tokio(resolver_config, ResolverOpts::default()).unwrap();
+    let mut next_resolver: usize = 0;
+    let record_type = args.record_type.map(RecordType::from);
+
+    // learn the wildcard baseline before draining the real tasks
+    let wildcard_baseline = detect_wildcard(&resolvers[0], &args.target, record_type).await;
+    if args.debug && !wildcard_baseline.is_empty() {
+        eprintln!("Wildcard baseline: {:?}", wildcard_baseline);
+    }
 
     let mut futures = vec![];
     let mut completed: usize = 0;
@@ -146,16 +491,58 @@ async fn main() {
         for _ in 0..std::cmp::min(n_new_tasks, pending_tasks.len()) {
             if let Some(task) = pending_tasks.pop_back() {
                 last_future_created = Instant::now();
-                futures.push(resolve(&resolver, task, &args.target).boxed());
+                // round-robin: dispatch each new future to the next resolver
+                let resolver = &resolvers[next_resolver % resolvers.len()];
+                next_resolver = next_resolver.wrapping_add(1);
+                futures.push(resolve(resolver, task, &args.target, record_type).boxed());
             }
         }
 
         // get some results
         if futures.len() > 0 {
-            let (result, _, remaining_futures) = select_all(futures).await;
+            let (mut result, _, remaining_futures) = select_all(futures).await;
             completed += 1;
-            println!("{}.{} {:?}", result.subdomain, args.target, result.status);
+
+            // demote catch-all hits whose addresses fall within the baseline
+            if matches!(result.status, ResolveStatus::Resolved)
+                && !result.addresses.is_empty()
+                && result
+                    .addresses
+                    .iter()
+                    .all(|a| wildcard_baseline.contains(a))
+            {
+                result.status = ResolveStatus::Wildcard;
+            }
+
             futures = remaining_futures;
+
+            if args.hide_wildcard && matches!(result.status, ResolveStatus::Wildcard) {
+                continue;
+            }
+
+            let mut detail = String::new();
+            if !result.addresses.is_empty() {
+                let ordered = interleave_addresses(&result.addresses, !args.prefer_ipv4);
+                let rendered: Vec<String> = ordered.iter().map(|a| a.to_string()).collect();
+                detail = rendered.join(", ");
+            } else if !result.records.is_empty() {
+                detail = result.records.join(", ");
+            }
+            if let Some(cname) = &result.cname_target {
+                detail = if detail.is_empty() {
+                    format!("CNAME -> {}", cname)
+                } else {
+                    format!("{} (CNAME -> {})", detail, cname)
+                };
+            }
+            if detail.is_empty() {
+                println!("{}.{} {:?}", result.subdomain, args.target, result.status);
+            } else {
+                println!(
+                    "{}.{} {:?} {}",
+                    result.subdomain, args.target, result.status, detail
+                );
+            }
         }
 
         // only sleep if we are waiting for new tasks
@@ -180,3 +567,70 @@ async fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn interleave_v6_first_by_default() {
+        let addrs = [ip("1.1.1.1"), ip("2606::1"), ip("1.0.0.1"), ip("2606::2")];
+        let ordered = interleave_addresses(&addrs, true);
+        assert_eq!(
+            ordered,
+            vec![ip("2606::1"), ip("1.1.1.1"), ip("2606::2"), ip("1.0.0.1")]
+        );
+    }
+
+    #[test]
+    fn interleave_v4_first_when_preferred() {
+        let addrs = [ip("1.1.1.1"), ip("2606::1"), ip("1.0.0.1"), ip("2606::2")];
+        let ordered = interleave_addresses(&addrs, false);
+        assert_eq!(
+            ordered,
+            vec![ip("1.1.1.1"), ip("2606::1"), ip("1.0.0.1"), ip("2606::2")]
+        );
+    }
+
+    #[test]
+    fn interleave_drains_other_family_when_uneven() {
+        let addrs = [ip("2606::1"), ip("2606::2"), ip("1.1.1.1")];
+        let ordered = interleave_addresses(&addrs, true);
+        assert_eq!(ordered, vec![ip("2606::1"), ip("1.1.1.1"), ip("2606::2")]);
+    }
+
+    #[test]
+    fn interleave_single_family_preserves_order() {
+        let v4 = [ip("1.1.1.1"), ip("1.0.0.1")];
+        assert_eq!(interleave_addresses(&v4, true), v4.to_vec());
+
+        let v6 = [ip("2606::1"), ip("2606::2")];
+        assert_eq!(interleave_addresses(&v6, false), v6.to_vec());
+    }
+
+    #[test]
+    fn interleave_empty_is_empty() {
+        assert!(interleave_addresses(&[], true).is_empty());
+        assert!(interleave_addresses(&[], false).is_empty());
+    }
+
+    #[test]
+    fn ip_strategy_mapping() {
+        assert_eq!(IpVersion::V4.ip_strategy(), LookupIpStrategy::Ipv4Only);
+        assert_eq!(IpVersion::V6.ip_strategy(), LookupIpStrategy::Ipv6Only);
+        assert_eq!(IpVersion::Both.ip_strategy(), LookupIpStrategy::Ipv4AndIpv6);
+    }
+
+    #[test]
+    fn random_label_is_deterministic_and_well_formed() {
+        let label = random_label(42);
+        assert_eq!(label, random_label(42));
+        assert_eq!(label.len(), 16);
+        assert!(label.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+        assert_ne!(random_label(1), random_label(2));
+    }
+}